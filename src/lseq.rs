@@ -39,10 +39,12 @@
 /// Contains the implementation of the exponential tree for LSeq
 pub mod ident;
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use ident::*;
 use serde::{Deserialize, Serialize};
 
-use crate::traits::CmRDT;
+use crate::traits::{CmRDT, CvRDT};
 
 use crate::vclock::Dot;
 
@@ -69,10 +71,16 @@ impl Default for SiteId {
 /// An `Entry` to the LSEQ consists of:
 #[derive(Debug, Clone)]
 pub struct Entry<T, A: Ident> {
-    /// The identifier of the entry.
+    /// The current position of the entry. This can change over the life of
+    /// the entry via `Op::Move`, unlike `dot` which is its stable identity.
     pub id: Identifier<A>,
-    /// The site id of the entry.
+    /// The site id of the entry, i.e. the dot of the insertion that created
+    /// it. This never changes, so it's what `Op::Move` and `Op::Delete` use
+    /// to refer back to the entry regardless of its current position.
     pub dot: Dot<A>,
+    /// Lamport timestamp `(counter, site)` of the last accepted write to
+    /// `id`, used to resolve concurrent `Op::Move`s of the same entry.
+    pub pos_ts: (u64, A),
     /// The element for the entry.
     pub c: T,
 }
@@ -86,6 +94,10 @@ pub struct LSeq<T, A: Ident> {
     seq: Vec<Entry<T, A>>,
     gen: IdentGen<A>,
     dot: Dot<A>,
+    /// Dots of entries that have been deleted, kept around so that a
+    /// `merge` of state from a replica that hasn't seen the delete yet
+    /// doesn't resurrect the entry.
+    tombstones: BTreeSet<Dot<A>>,
 }
 
 /// Operations that can be performed on an LSeq tree
@@ -112,6 +124,20 @@ pub enum Op<T, A: Ident> {
         /// id of site that issued delete
         dot: Dot<A>,
     },
+    /// Move an element to a new position
+    Move {
+        /// The stable identity (insertion dot) of the element being moved
+        remote: Dot<A>,
+        /// The newly allocated target position
+        #[serde(flatten)]
+        id: Identifier<A>,
+        /// Lamport timestamp `(counter, site)` of this move, used to
+        /// resolve concurrent moves of the same element (ties broken by
+        /// site)
+        ts: (u64, A),
+        /// clock of site that issued the move
+        dot: Dot<A>,
+    },
 }
 
 impl<T, A: Ident> LSeq<T, A> {
@@ -121,23 +147,65 @@ impl<T, A: Ident> LSeq<T, A> {
             seq: Vec::new(),
             gen: IdentGen::new(id.clone()),
             dot: Dot::new(id, 0),
+            tombstones: BTreeSet::new(),
         }
     }
 
     /// Insert an identifier and value in the LSEQ
     pub fn insert(&mut self, ix: Identifier<A>, dot: Dot<A>, c: T) {
-        // Inserts only have an impact if the identifier is in the tree
+        // Inserts only have an impact if the identifier is not already in the tree, and
+        // if it hasn't already been deleted by a concurrent op.
+        if self.tombstones.contains(&dot) {
+            return;
+        }
         if let Err(res) = self.seq.binary_search_by(|e| e.id.cmp(&ix)) {
-            self.seq.insert(res, Entry { id: ix, dot, c });
+            let pos_ts = (dot.counter, dot.actor.clone());
+            self.seq.insert(
+                res,
+                Entry {
+                    id: ix,
+                    dot,
+                    pos_ts,
+                    c,
+                },
+            );
         }
     }
 
-    /// Remove an identifier from the LSEQ
-    pub fn delete(&mut self, ix: Identifier<A>) {
-        // Deletes only have an effect if the identifier is already in the tree
-        if let Ok(i) = self.seq.binary_search_by(|e| e.id.cmp(&ix)) {
+    /// Remove the entry whose stable insertion `Dot` is `remote`, recording
+    /// it as a tombstone.
+    ///
+    /// Matches by `remote` rather than by `id`: `Op::Move` can change an
+    /// entry's `id` without the replica applying this delete having seen
+    /// that move yet, so a lookup by `id` could silently miss the entry.
+    pub fn delete(&mut self, remote: Dot<A>) {
+        // Deletes only have an effect if the entry is still in the tree
+        if let Some(i) = self.seq.iter().position(|e| e.dot == remote) {
             self.seq.remove(i);
         }
+        self.tombstones.insert(remote);
+    }
+
+    /// Move the entry identified by `remote` (its stable insertion `Dot`)
+    /// to `pos`, but only if `ts` is newer than the entry's current
+    /// position timestamp (ties broken by `SiteId`). This guarantees that
+    /// concurrent moves of the same element resolve to exactly one
+    /// surviving position with no duplication or deletion.
+    ///
+    /// A move for an entry this replica doesn't know about (not yet seen,
+    /// or already deleted) is a no-op.
+    pub fn reposition(&mut self, remote: Dot<A>, pos: Identifier<A>, ts: (u64, A)) {
+        let moved = match self.seq.iter_mut().find(|e| e.dot == remote) {
+            Some(entry) if ts > entry.pos_ts => {
+                entry.id = pos;
+                entry.pos_ts = ts;
+                true
+            }
+            _ => false,
+        };
+        if moved {
+            self.seq.sort_by(|a, b| a.id.cmp(&b.id));
+        }
     }
 
     /// Perform a local insertion of an element at a given position.
@@ -181,7 +249,6 @@ impl<T, A: Ident> LSeq<T, A> {
             dot: self.dot.clone(),
             c,
         };
-        self.dot.counter += 1;
         self.apply(op.clone());
         op
     }
@@ -206,12 +273,143 @@ impl<T, A: Ident> LSeq<T, A> {
             dot: self.dot.clone(),
         };
 
-        self.dot.counter += 1;
         self.apply(op.clone());
 
         Some(op)
     }
 
+    /// Perform a local move of the entry at `from` to `to`.
+    ///
+    /// The target position is allocated the same way [`insert_index`]
+    /// would for `to` if the entry at `from` had already been removed, so
+    /// moving past the end of the LSeq appends it. Returns `None` if
+    /// `from` is out of bounds.
+    ///
+    /// [`insert_index`]: LSeq::insert_index
+    pub fn move_index(&mut self, from: usize, to: usize) -> Option<Op<T, A>>
+    where
+        T: Clone,
+    {
+        if from >= self.seq.len() {
+            return None;
+        }
+
+        let remote = self.seq[from].dot.clone();
+        let rest: Vec<&Identifier<A>> = self
+            .seq
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| if i == from { None } else { Some(&e.id) })
+            .collect();
+
+        let lower = self.gen.lower();
+        let upper = self.gen.upper();
+
+        let id = if rest.len() <= to {
+            let prev = rest.last().copied().unwrap_or(&lower);
+            self.gen.alloc(prev, &upper)
+        } else {
+            let prev = match to.checked_sub(1) {
+                Some(i) => rest[i],
+                None => &lower,
+            };
+            let next = rest.get(to).copied().unwrap_or(&upper);
+            self.gen.alloc(prev, next)
+        };
+
+        let ts = (self.dot.counter, self.dot.actor.clone());
+        let op = Op::Move {
+            remote,
+            id,
+            ts,
+            dot: self.dot.clone(),
+        };
+        self.apply(op.clone());
+
+        Some(op)
+    }
+
+    /// Perform a local bulk insertion of `items` starting at `ix`.
+    ///
+    /// Unlike calling [`insert_index`](LSeq::insert_index) once per item,
+    /// this allocates the whole run of identifiers in a single descent of
+    /// the tree, which is a large constant-factor speedup when pasting a
+    /// big chunk of text. If `ix` is greater than the length of the LSeq
+    /// then the items are appended to the end.
+    pub fn insert_range(&mut self, ix: usize, items: impl IntoIterator<Item = T>) -> Vec<Op<T, A>>
+    where
+        T: Clone,
+    {
+        let items: Vec<T> = items.into_iter().collect();
+        if items.is_empty() {
+            return Vec::new();
+        }
+
+        let lower = self.gen.lower();
+        let upper = self.gen.upper();
+
+        let idents = if self.seq.len() <= ix {
+            let prev = self.seq.last().map(|Entry { id, .. }| id).unwrap_or(&lower);
+            self.gen.alloc_range(prev, &upper, items.len())
+        } else {
+            let prev = match ix.checked_sub(1) {
+                Some(i) => &self.seq.get(i).unwrap().id,
+                None => &lower,
+            };
+            let next = self
+                .seq
+                .get(ix)
+                .map(|Entry { id, .. }| id)
+                .unwrap_or(&upper);
+            self.gen.alloc_range(prev, next, items.len())
+        };
+
+        let mut ops = Vec::with_capacity(items.len());
+        for (id, c) in idents.into_iter().zip(items) {
+            let op = Op::Insert {
+                id,
+                dot: self.dot.clone(),
+                c,
+            };
+            self.apply(op.clone());
+            ops.push(op);
+        }
+        ops
+    }
+
+    /// Perform a local bulk deletion of the contiguous `range` of indices.
+    ///
+    /// Unlike calling [`delete_index`](LSeq::delete_index) once per index,
+    /// this clamps `range` to the current length and splices the whole span
+    /// out of the sequence in one go, rather than shifting it down index by
+    /// index.
+    pub fn delete_range(&mut self, range: std::ops::Range<usize>) -> Vec<Op<T, A>>
+    where
+        T: Clone,
+    {
+        let start = range.start.min(self.seq.len());
+        let end = range.end.min(self.seq.len());
+        if start >= end {
+            return Vec::new();
+        }
+
+        let removed: Vec<Entry<T, A>> = self.seq.splice(start..end, std::iter::empty()).collect();
+
+        let mut ops = Vec::with_capacity(removed.len());
+        for entry in removed {
+            self.tombstones.insert(entry.dot.clone());
+            let dot = self.dot.clone();
+            self.observe(dot.counter);
+            let op = Op::Delete {
+                id: entry.id,
+                remote: entry.dot,
+                dot,
+            };
+            ops.push(op);
+        }
+        ops
+    }
+
     /// Get the length of the LSEQ.
     pub fn len(&self) -> usize {
         self.seq.len()
@@ -231,6 +429,15 @@ impl<T, A: Ident> LSeq<T, A> {
     pub fn raw_entries(&self) -> &Vec<Entry<T, A>> {
         &self.seq
     }
+
+    /// Advance `self.dot`'s counter as a Lamport clock: past every counter
+    /// this replica has seen, whether from an op it's applying or one it's
+    /// about to issue itself. Called once per applied/issued op so that a
+    /// move stamped with the resulting counter is always newer than the
+    /// `pos_ts` of any entry this replica already knows about.
+    fn observe(&mut self, counter: u64) {
+        self.dot.counter = self.dot.counter.max(counter) + 1;
+    }
 }
 
 impl<T, A: Ident> CmRDT for LSeq<T, A> {
@@ -244,8 +451,60 @@ impl<T, A: Ident> CmRDT for LSeq<T, A> {
     /// result is a no-op
     fn apply(&mut self, op: Self::Op) {
         match op {
-            Op::Insert { id, dot, c } => self.insert(id, dot, c),
-            Op::Delete { id, .. } => self.delete(id),
+            Op::Insert { id, dot, c } => {
+                self.observe(dot.counter);
+                self.insert(id, dot, c);
+            }
+            Op::Delete { remote, dot, .. } => {
+                self.observe(dot.counter);
+                self.delete(remote);
+            }
+            Op::Move {
+                remote,
+                id,
+                ts,
+                dot,
+            } => {
+                self.observe(dot.counter.max(ts.0));
+                self.reposition(remote, id, ts);
+            }
+        }
+    }
+}
+
+impl<T: Clone, A: Ident> CvRDT for LSeq<T, A> {
+    /// Merge the state of `other` into `self`.
+    ///
+    /// The merged sequence is the union of both sides' entries, keyed by
+    /// the `Dot` each entry was inserted with: an entry present on either
+    /// side is kept unless its dot is tombstoned (deleted) on either side,
+    /// which guarantees that `a.merge(b)` converges to the same sequence
+    /// as replaying every `Op` from both replicas in causal order.
+    fn merge(&mut self, other: &Self) {
+        self.tombstones.extend(other.tombstones.iter().cloned());
+        // `other.dot.counter` is past every counter `other` has seen, so this
+        // keeps our own future moves newer than any entry taken in below.
+        self.dot.counter = self.dot.counter.max(other.dot.counter);
+
+        // Union by stable dot, keeping the entry with the greater `pos_ts`
+        // (ties broken by site) when the same dot appears on both sides.
+        // This mirrors `reposition` so that a merge agrees with whichever
+        // side would win if the losing side's `Op::Move` were replayed.
+        let mut by_dot: BTreeMap<Dot<A>, Entry<T, A>> = BTreeMap::new();
+        for entry in self.seq.iter().chain(other.seq.iter()) {
+            if self.tombstones.contains(&entry.dot) {
+                continue;
+            }
+            match by_dot.get(&entry.dot) {
+                Some(existing) if existing.pos_ts >= entry.pos_ts => {}
+                _ => {
+                    by_dot.insert(entry.dot.clone(), entry.clone());
+                }
+            }
         }
+
+        let mut merged: Vec<Entry<T, A>> = by_dot.into_iter().map(|(_, entry)| entry).collect();
+        merged.sort_by(|a, b| a.id.cmp(&b.id));
+        self.seq = merged;
     }
 }